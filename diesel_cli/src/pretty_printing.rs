@@ -1,8 +1,89 @@
-use std::fmt::{Write, Error as FmtError};
+use std::fmt::Error as FmtError;
+
+/// The whitespace used to represent a single level of indentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// Indent with `indent_width` space characters.
+    Spaces,
+    /// Indent with a single tab character, regardless of `indent_width`.
+    Tabs,
+}
+
+/// Configuration for [`format_schema_with`], modeled after rustfmt's own
+/// configuration options.
+///
+/// Use [`FormatOptions::default`] to get the same output as the plain
+/// [`format_schema`] function, then override whichever knobs matter to you
+/// through the builder methods.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    indent_style: IndentStyle,
+    indent_width: usize,
+    max_width: Option<usize>,
+    trailing_comma: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_style: IndentStyle::Spaces,
+            indent_width: 4,
+            max_width: None,
+            trailing_comma: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Creates a new `FormatOptions` with the same defaults as
+    /// [`format_schema`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether indentation is made up of spaces or tabs.
+    pub fn indent_style(mut self, indent_style: IndentStyle) -> Self {
+        self.indent_style = indent_style;
+        self
+    }
+
+    /// Sets the number of spaces per indentation level. Ignored when
+    /// `indent_style` is [`IndentStyle::Tabs`].
+    pub fn indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+
+    /// Sets the column at which long lines (e.g. a column's fully qualified
+    /// type) should be wrapped. `None` disables wrapping.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Sets whether a trailing comma is forced after the last item of a
+    /// `{...}` body whose items are already comma-separated. A body with a
+    /// single item and no comma is left without one.
+    pub fn trailing_comma(mut self, trailing_comma: bool) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    /// The literal string inserted for a single indentation level.
+    fn indent_unit(&self) -> String {
+        match self.indent_style {
+            IndentStyle::Spaces => " ".repeat(self.indent_width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
 
 /// Simple pretty printer hand tailored for the output generated by the `quote`
 /// crate for schema inference.
 ///
+/// Uses the default [`FormatOptions`]. Use [`format_schema_with`] to customize
+/// the indentation style, line width, or trailing comma behavior.
+///
 /// # Rules
 ///
 /// 1. Seeing `{` increases indentation level
@@ -13,103 +94,816 @@ use std::fmt::{Write, Error as FmtError};
 ///   - between path segments and `::`
 ///   - after `(`, '<' and before `)`, `>`
 ///   - before `,`
-pub fn format_schema(schema: &str) -> Result<String, FmtError> {
-    let mut out = String::with_capacity(schema.len());
-    let mut indent = String::new();
-    let mut skip_space = false;
-    let mut last_char = ' ';
-    let mut inside_parenthesis = false;
-
-    for c in schema.chars() {
-        // The `quote!` macro inserts whitespaces at some strange location,
-        // let's remove them!
-        match c {
-            '!' | ',' | '<' | ')' | '>' if last_char.is_whitespace() => {
-                out.pop();
-            }
-            ':' if last_char.is_whitespace() => {
-                // Unless we are at the beginning of a fully qualified path,
-                // remove the whitespace.
-                let char_before_whitespace = {
-                    let mut chars = out.chars();
-                    chars.next_back();
-                    chars.next_back()
-                };
+/// 5. `///` and `/** */` doc comments are left untouched except for
+///    re-indenting their continuation lines to the surrounding nesting depth
+pub fn format_schema(schema: &str) -> Result<(String, Vec<RenamedIdent>), FmtError> {
+    format_schema_with(schema, &FormatOptions::default())
+}
+
+/// Like [`format_schema`], but driven by an explicit [`FormatOptions`]
+/// instead of the built-in defaults.
+///
+/// Rather than patching up whitespace in the raw `quote!` output
+/// character-by-character, this tokenizes `schema` into a tree of idents,
+/// puncts and `(`/`<`/`{`-delimited groups, then walks that tree to emit
+/// properly nested, indented output. Working off a real tree (instead of a
+/// single "are we inside parens" flag) is what lets nesting of arbitrary
+/// depth - `Array<Nullable<Integer>>`, a generic argument inside a tuple,
+/// and so on - come out indented correctly.
+///
+/// Before emitting, every `table!` invocation's table and column names are
+/// run through [`sanitize_ident`]/[`sanitize_idents`]: any name that isn't a
+/// valid, non-keyword Rust identifier is rewritten and gets a
+/// `#[sql_name = "..."]` attribute inserted so the generated code still
+/// queries the real column. The full list of renames is returned alongside
+/// the formatted string so the caller can print a summary.
+pub fn format_schema_with(
+    schema: &str,
+    opts: &FormatOptions,
+) -> Result<(String, Vec<RenamedIdent>), FmtError> {
+    let mut tokens = tokenize(schema);
+    let renamed = sanitize_schema_tokens(&mut tokens);
+
+    let mut emitter = Emitter::new(opts);
+    emitter.emit_items(&tokens, "", false);
+    let out = emitter.out.replace("table!", "\ntable!").trim().to_string();
+
+    let out = match opts.max_width {
+        Some(max_width) => wrap_long_lines(&out, opts, max_width),
+        None => out,
+    };
+
+    Ok((out, renamed))
+}
+
+/// A single node of the token tree `format_schema_with` formats: either a
+/// leaf (an identifier or a piece of punctuation) or a group of tokens
+/// enclosed by a matching pair of delimiters.
+#[derive(Debug, Clone)]
+enum Token {
+    Ident(String),
+    Colon,
+    Comma,
+    Bang,
+    Arrow,
+    Paren(Vec<Token>),
+    Angle(Vec<Token>),
+    Brace(Vec<Token>),
+    DocComment(String),
+    /// A `#[...]` attribute inserted by [`sanitize_schema_tokens`] (e.g.
+    /// `#[sql_name = "user-id"]`); emitted on its own line like a doc
+    /// comment.
+    Attribute(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Ident,
+    Colon,
+    Comma,
+    Bang,
+    Arrow,
+    Paren,
+    Angle,
+    Brace,
+}
 
-                if char_before_whitespace != Some('>') {
-                    out.pop();
+impl TokenKind {
+    fn of(token: &Token) -> Self {
+        match token {
+            Token::Ident(_) => TokenKind::Ident,
+            Token::Colon => TokenKind::Colon,
+            Token::Comma => TokenKind::Comma,
+            Token::Bang => TokenKind::Bang,
+            Token::Arrow => TokenKind::Arrow,
+            Token::Paren(_) => TokenKind::Paren,
+            Token::Angle(_) => TokenKind::Angle,
+            Token::Brace(_) => TokenKind::Brace,
+            Token::DocComment(_) => unreachable!("doc comments are spaced on their own lines"),
+            Token::Attribute(_) => unreachable!("attributes are spaced on their own lines"),
+        }
+    }
+}
+
+/// Whether a space should be written between a token of kind `prev` and one
+/// of kind `current`. This is where rules 4 from [`format_schema`]'s doc
+/// comment are encoded.
+fn needs_space(prev: Option<TokenKind>, current: TokenKind) -> bool {
+    use TokenKind::*;
+
+    match current {
+        Comma | Bang | Angle => return false,
+        // A `::` immediately after `->`, a closing `>` or a `,` keeps its
+        // leading space (`-> ::diesel::...`); anywhere else in a path it's
+        // dropped.
+        Colon => return matches!(prev, Some(Arrow) | Some(Angle) | Some(Comma)),
+        _ => {}
+    }
+
+    !matches!(prev, None | Some(Colon))
+}
+
+/// The punctuation characters that always start (and end) their own token,
+/// regardless of what's next to them. Everything else that isn't whitespace
+/// gets glued onto a name-like run by [`tokenize`] (a `/` or `#` can only
+/// start such a run, never continue one) - which is what lets a
+/// non-Rust-identifier name like `user-id` come through as a single ident
+/// instead of `user`, `-`, `id`.
+fn is_structural(c: char) -> bool {
+    matches!(c, '(' | ')' | '<' | '>' | '{' | '}' | ':' | ',' | '!')
+}
+
+/// Splits `schema` into a tree of [`Token`]s: `(`, `<` and `{` each open a
+/// group that's closed by the matching `)`, `>` or `}`; `///`/`/** */`
+/// comments and `#[...]` attributes are captured whole instead of being
+/// tokenized character by character (the latter is what keeps re-running
+/// the formatter over its own `#[sql_name = "..."]` output a no-op); and a
+/// run of characters that isn't whitespace, delimiter punctuation, `->`, `/`
+/// or `#` is kept together as one [`Token::Ident`] even
+/// when it contains characters like `-` that aren't valid in a Rust
+/// identifier - so a schema name with one can still be
+/// sanitized as a single unit instead of being split apart.
+fn tokenize(schema: &str) -> Vec<Token> {
+    let chars: Vec<char> = schema.chars().collect();
+    let mut stack: Vec<Vec<Token>> = vec![Vec::new()];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '/' && chars.get(i + 1) == Some(&'/') && chars.get(i + 2) == Some(&'/') {
+            let start = i;
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            stack
+                .last_mut()
+                .unwrap()
+                .push(Token::DocComment(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let start = i;
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            stack
+                .last_mut()
+                .unwrap()
+                .push(Token::DocComment(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        // A `#[sql_name = "..."]` attribute inserted by a previous formatting
+        // pass: captured whole (bracket-depth matched, not string-literal
+        // aware) so re-feeding already-sanitized output back in doesn't
+        // decompose it into bogus idents or re-mangle the name inside the
+        // string.
+        if c == '#' && chars.get(i + 1) == Some(&'[') {
+            let start = i;
+            i += 1;
+            let mut depth = 0usize;
+            while i < chars.len() {
+                match chars[i] {
+                    '[' => depth += 1,
+                    ']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            i += 1;
+                            break;
+                        }
+                    }
+                    _ => {}
                 }
+                i += 1;
             }
-            _ => {}
+            stack
+                .last_mut()
+                .unwrap()
+                .push(Token::Attribute(chars[start..i].iter().collect()));
+            continue;
         }
 
-        if skip_space && c.is_whitespace() && last_char != '>' {
+        if c == '-' && chars.get(i + 1) == Some(&'>') {
+            stack.last_mut().unwrap().push(Token::Arrow);
+            i += 2;
             continue;
         }
 
-        last_char = c;
-        skip_space = false;
+        match c {
+            '(' | '<' | '{' => {
+                stack.push(Vec::new());
+                i += 1;
+            }
+            ')' | '>' | '}' => {
+                let group = stack.pop().unwrap_or_default();
+                let token = match c {
+                    ')' => Token::Paren(group),
+                    '>' => Token::Angle(group),
+                    _ => Token::Brace(group),
+                };
+                // An unmatched closing delimiter leaves nothing on the stack
+                // to attach to; rather than panic, start a fresh outermost
+                // frame so the rest of the (malformed) input still tokenizes.
+                if stack.is_empty() {
+                    stack.push(Vec::new());
+                }
+                stack.last_mut().unwrap().push(token);
+                i += 1;
+            }
+            ':' => {
+                stack.last_mut().unwrap().push(Token::Colon);
+                i += 1;
+            }
+            ',' => {
+                stack.last_mut().unwrap().push(Token::Comma);
+                i += 1;
+            }
+            '!' => {
+                stack.last_mut().unwrap().push(Token::Bang);
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    let ch = chars[i];
+                    if ch.is_whitespace() || is_structural(ch) || ch == '/' || ch == '#' {
+                        break;
+                    }
+                    if ch == '-' && chars.get(i + 1) == Some(&'>') {
+                        break;
+                    }
+                    i += 1;
+                }
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .push(Token::Ident(chars[start..i].iter().collect()));
+            }
+        }
+    }
+
+    // Malformed/unbalanced input would leave more than one frame on the
+    // stack; fall back to the outermost one rather than panicking.
+    stack.into_iter().next().unwrap_or_default()
+}
 
-        // At this point, there is an empty line before `}`. We need to remove
-        // the already inserted indent, because the new indent is smaller than
-        // the old one.
-        if c == '}' {
-            while let Some(c) = out.pop() {
-                if c == '\n' {
-                    break;
+/// Walks a [`Token`] tree, writing indented, rule-4-compliant output into
+/// `out`.
+struct Emitter<'a> {
+    out: String,
+    opts: &'a FormatOptions,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(opts: &'a FormatOptions) -> Self {
+        Emitter {
+            out: String::new(),
+            opts,
+        }
+    }
+
+    /// Emits a doc comment's text verbatim, re-indenting any continuation
+    /// lines a `/** */` block comment carries to `indent`.
+    fn emit_doc_comment(&mut self, text: &str, indent: &str) {
+        let mut lines = text.split('\n');
+        if let Some(first) = lines.next() {
+            self.out.push_str(first);
+        }
+        for line in lines {
+            self.out.push('\n');
+            self.out.push_str(indent);
+            self.out.push_str(line.trim_start());
+        }
+    }
+
+    /// Emits a flat run of tokens with no line breaks of its own except
+    /// around doc comments and attributes and after a `}`: the contents of a
+    /// `(...)`/`<...>` group, or a single comma-separated item inside a
+    /// `{...}` body.
+    fn emit_inline(&mut self, tokens: &[Token], indent: &str) {
+        let mut prev_kind: Option<TokenKind> = None;
+
+        for (idx, token) in tokens.iter().enumerate() {
+            if let Token::DocComment(text) = token {
+                if prev_kind.is_some() {
+                    self.out.push('\n');
+                    self.out.push_str(indent);
+                }
+                self.emit_doc_comment(text, indent);
+                self.out.push('\n');
+                self.out.push_str(indent);
+                prev_kind = None;
+                continue;
+            }
+
+            if let Token::Attribute(text) = token {
+                if prev_kind.is_some() {
+                    self.out.push('\n');
+                    self.out.push_str(indent);
+                }
+                self.out.push_str(text);
+                self.out.push('\n');
+                self.out.push_str(indent);
+                prev_kind = None;
+                continue;
+            }
+
+            let kind = TokenKind::of(token);
+            if needs_space(prev_kind, kind) {
+                self.out.push(' ');
+            }
+
+            match token {
+                Token::Ident(s) => self.out.push_str(s),
+                Token::Colon => self.out.push(':'),
+                Token::Bang => self.out.push('!'),
+                Token::Arrow => self.out.push_str("->"),
+                Token::Comma => self.out.push(','),
+                Token::Paren(inner) => {
+                    self.out.push('(');
+                    self.emit_inline(inner, indent);
+                    self.out.push(')');
+                }
+                Token::Angle(inner) => {
+                    self.out.push('<');
+                    self.emit_inline(inner, indent);
+                    self.out.push('>');
                 }
+                Token::Brace(inner) => {
+                    self.emit_brace(inner, indent);
+                    // Rule 3: whatever follows a closing `}` starts a new line.
+                    if idx + 1 < tokens.len() {
+                        self.out.push('\n');
+                        self.out.push_str(indent);
+                        prev_kind = None;
+                        continue;
+                    }
+                }
+                Token::DocComment(_) => unreachable!(),
+                Token::Attribute(_) => unreachable!(),
             }
 
-            indent.pop();
-            write!(out, "\n{}", indent)?;
+            prev_kind = Some(kind);
         }
+    }
 
-        // Keep track of our parenthesis level
-        match c {
-            '(' => inside_parenthesis = true,
-            ')' => inside_parenthesis = false,
-             _ => {}
+    /// Emits the body of a `{...}` group (or the top-level schema, which is
+    /// treated the same way): each top-level comma starts a new line at
+    /// `indent`. `force_trailing_comma` adds a missing trailing comma to a
+    /// body that already uses commas to separate its items.
+    fn emit_items(&mut self, tokens: &[Token], indent: &str, force_trailing_comma: bool) {
+        let has_comma = tokens.iter().any(|t| matches!(t, Token::Comma));
+        let mut last_was_comma = false;
+        let mut wrote_anything = false;
+        let mut i = 0;
+
+        while i < tokens.len() {
+            if matches!(tokens[i], Token::Comma) {
+                self.out.push(',');
+                self.out.push('\n');
+                self.out.push_str(indent);
+                last_was_comma = true;
+                wrote_anything = true;
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < tokens.len() && !matches!(tokens[i], Token::Comma) {
+                i += 1;
+            }
+            self.emit_inline(&tokens[start..i], indent);
+            last_was_comma = false;
+            wrote_anything = true;
+        }
+
+        // A doc comment or attribute is always emitted with its own trailing
+        // "\n<indent>" (so whatever follows it starts on a fresh line). When
+        // one is the very last thing in this body, that trailing newline is
+        // just as dangling as the one a trailing comma leaves - nothing is
+        // coming to use it - so it needs the same treatment: no comma forced
+        // after it, and it gets trimmed below rather than left as a blank,
+        // indented line before the closing `}`.
+        let ends_with_comment = matches!(
+            tokens.last(),
+            Some(Token::DocComment(_)) | Some(Token::Attribute(_))
+        );
+        let dangling_newline = last_was_comma || ends_with_comment;
+
+        if wrote_anything && force_trailing_comma && has_comma && !dangling_newline {
+            self.out.push(',');
+        }
+
+        if dangling_newline {
+            // Undo the dangling "\n<indent>": whatever comes next (typically
+            // the closing `}`) writes its own.
+            let trim_to = self.out.len() - (indent.len() + 1);
+            self.out.truncate(trim_to);
+        }
+    }
+
+    fn emit_brace(&mut self, inner: &[Token], indent: &str) {
+        self.out.push('{');
+
+        if inner.is_empty() {
+            self.out.push('\n');
+            self.out.push_str(indent);
+        } else {
+            let inner_indent = format!("{}{}", indent, self.opts.indent_unit());
+            self.out.push('\n');
+            self.out.push_str(&inner_indent);
+            self.emit_items(inner, &inner_indent, self.opts.trailing_comma);
+            self.out.push('\n');
+            self.out.push_str(indent);
+        }
+
+        self.out.push('}');
+    }
+}
+
+/// Breaks any `name -> type,` line wider than `max_width` after the `->`,
+/// continuing one indentation level deeper than the column itself. Nested
+/// generic arguments (`Nullable<Array<...>>`) are further broken at `<`/`,`
+/// boundaries with a hanging indent one level per nesting depth.
+///
+/// Lines that are already narrow enough, that don't look like a column
+/// definition, or that belong to a doc comment/attribute are passed through
+/// unchanged: a doc comment happening to contain `" -> "` (e.g. describing a
+/// column rename) must never be split, since that would strip its `///`
+/// marker and leave bare tokens the `table!` macro can't parse.
+fn wrap_long_lines(formatted: &str, opts: &FormatOptions, max_width: usize) -> String {
+    let indent_unit = opts.indent_unit();
+    let mut out = String::with_capacity(formatted.len());
+    let mut in_block_comment = false;
+
+    for (i, line) in formatted.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+
+        let trimmed = line.trim_start();
+        if in_block_comment {
+            out.push_str(line);
+            if trimmed.contains("*/") {
+                in_block_comment = false;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("///") || trimmed.starts_with("#[") {
+            out.push_str(line);
+            continue;
+        }
+
+        if trimmed.starts_with("/**") {
+            out.push_str(line);
+            in_block_comment = !trimmed.contains("*/");
+            continue;
+        }
+
+        if line.chars().count() <= max_width {
+            out.push_str(line);
+            continue;
+        }
+
+        let indent_len = line.len() - line.trim_start().len();
+        let (indent, rest) = line.split_at(indent_len);
+
+        match rest.find(" -> ") {
+            Some(arrow_pos) => {
+                let name = &rest[..arrow_pos];
+                let ty = &rest[arrow_pos + " -> ".len()..];
+
+                out.push_str(indent);
+                out.push_str(name);
+                out.push_str(" ->");
+
+                let continuation_indent = format!("{}{}", indent, indent_unit);
+                wrap_type(&mut out, ty, &continuation_indent, &indent_unit, max_width);
+            }
+            None => out.push_str(line),
+        }
+    }
+
+    out
+}
+
+/// Writes `ty` into `out`, breaking after a `<`/`,` at which the current line
+/// already exceeds `max_width`; a line can therefore run past `max_width` up
+/// to the next such boundary. Each nesting level introduced by `<...>` gets
+/// one more `indent_unit` of hanging indent.
+fn wrap_type(out: &mut String, ty: &str, base_indent: &str, indent_unit: &str, max_width: usize) {
+    out.push('\n');
+    out.push_str(base_indent);
+
+    let mut depth = 0usize;
+    let mut current_indent = base_indent.to_string();
+    let mut col = current_indent.chars().count();
+
+    let chars: Vec<char> = ty.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // A comma inside a multi-arg generic is always followed by the
+        // single space `emit_inline` put there; that separator is
+        // re-emitted explicitly below (as a space or a line break), so skip
+        // the literal one from `ty` instead of also copying it through.
+        if c == ' ' && chars.get(i.wrapping_sub(1)) == Some(&',') {
+            i += 1;
+            continue;
         }
 
-        write!(out, "{}", c)?;
+        out.push(c);
+        col += 1;
 
-        // We need to insert newlines in some places and adjust the indent.
-        // Also, we need to remember if we could skip the next whitespace.
         match c {
-            ',' => {
-                if !inside_parenthesis {
-                    skip_space = true;
-                    write!(out, "\n{}", indent)?;
+            '<' => {
+                depth += 1;
+                current_indent = format!("{}{}", base_indent, indent_unit.repeat(depth));
+                if col > max_width {
+                    out.push('\n');
+                    out.push_str(&current_indent);
+                    col = current_indent.chars().count();
                 }
-            },
-            '}' => {
-                skip_space = true;
-                write!(out, "\n{}", indent)?;
             }
-            '{' => {
-                skip_space = true;
-                indent += "\t";
-                write!(out, "\n{}", indent)?;
+            '>' => {
+                depth = depth.saturating_sub(1);
+                current_indent = format!("{}{}", base_indent, indent_unit.repeat(depth));
+            }
+            ',' if depth > 0 => {
+                if col > max_width {
+                    out.push('\n');
+                    out.push_str(&current_indent);
+                    col = current_indent.chars().count();
+                } else {
+                    out.push(' ');
+                    col += 1;
+                }
             }
-            ':' | '(' | '<' => skip_space = true,
             _ => {}
         }
+
+        i += 1;
+    }
+}
+
+/// A table or column name that had to be rewritten to a valid Rust
+/// identifier, paired with the name it was rewritten to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedIdent {
+    pub original: String,
+    pub renamed: String,
+}
+
+/// Rewrites `name` into a valid, non-keyword Rust identifier if it isn't
+/// already one. Returns the identifier to emit, and, when a rewrite
+/// happened, the original name so the caller can attach a
+/// `#[sql_name = "..."]` attribute to keep queries hitting the real column.
+///
+/// Handles invalid characters (`user-id`), a leading digit (`2fa_enabled`),
+/// and Rust's reserved keywords (`type`).
+pub fn sanitize_ident(name: &str) -> (String, Option<String>) {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    if sanitized.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+
+    if is_reserved_keyword(&sanitized) {
+        sanitized.push('_');
+    }
+
+    if sanitized == name {
+        (sanitized, None)
+    } else {
+        (sanitized, Some(name.to_string()))
+    }
+}
+
+fn is_reserved_keyword(ident: &str) -> bool {
+    matches!(
+        ident,
+        "as" | "async" | "await" | "break" | "const" | "continue" | "crate" | "dyn" | "else"
+            | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop"
+            | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "self" | "Self"
+            | "static" | "struct" | "super" | "trait" | "true" | "type" | "unsafe" | "use"
+            | "where" | "while" | "abstract" | "become" | "box" | "do" | "final" | "macro"
+            | "override" | "priv" | "try" | "typeof" | "unsized" | "virtual" | "yield"
+    )
+}
+
+/// Sanitizes a batch of names (e.g. every column in a table) via
+/// [`sanitize_ident`], then disambiguates any collisions the rewrite
+/// introduced (`user-id` and `user_id` would otherwise both become
+/// `user_id`) by appending a numeric suffix.
+///
+/// Returns the sanitized identifiers in the same order as `names`, plus the
+/// full list of `(original, renamed)` pairs so the caller can print a rename
+/// report.
+pub fn sanitize_idents<'a, I>(names: I) -> (Vec<String>, Vec<RenamedIdent>)
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut seen = std::collections::HashSet::new();
+    let mut sanitized_names = Vec::new();
+    let mut renamed = Vec::new();
+
+    for name in names {
+        let (mut ident, _) = sanitize_ident(name);
+
+        if !seen.insert(ident.clone()) {
+            let mut suffix = 2;
+            loop {
+                let candidate = format!("{}_{}", ident, suffix);
+                if seen.insert(candidate.clone()) {
+                    ident = candidate;
+                    break;
+                }
+                suffix += 1;
+            }
+        }
+
+        if ident != name {
+            renamed.push(RenamedIdent {
+                original: name.to_string(),
+                renamed: ident.clone(),
+            });
+        }
+
+        sanitized_names.push(ident);
     }
 
-    Ok(out.replace("\t", "    ").replace("table!", "\ntable!").trim().to_string())
+    (sanitized_names, renamed)
 }
 
+/// Formats the `#[sql_name = "..."]` attribute diesel needs when the emitted
+/// identifier doesn't match `original`. `original` is escaped so a quoted
+/// identifier containing `"` or `\` (which some databases allow) still
+/// produces a valid string literal.
+pub fn sql_name_attribute(original: &str) -> String {
+    let escaped = original.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("#[sql_name = \"{}\"]", escaped)
+}
+
+/// Walks the top level of a tokenized schema looking for `table! { ... }`
+/// invocations, sanitizing each one's table and column names in place.
+/// Returns every rename that was made, across all tables, in source order.
+fn sanitize_schema_tokens(tokens: &mut [Token]) -> Vec<RenamedIdent> {
+    let mut renamed = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let is_table_macro = matches!(&tokens[i], Token::Ident(name) if name == "table")
+            && matches!(tokens.get(i + 1), Some(Token::Bang));
+
+        if is_table_macro {
+            if let Some(Token::Brace(inner)) = tokens.get_mut(i + 2) {
+                renamed.extend(sanitize_table_invocation(inner));
+            }
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    renamed
+}
+
+/// Sanitizes a single `table! { name (pk)? { columns } }` body in place:
+/// the table name and every column name are run through
+/// [`sanitize_ident`]/[`sanitize_idents`], a `#[sql_name = "..."]` attribute
+/// is inserted before each renamed identifier, and any reference to a
+/// renamed column in the primary-key tuple is rewritten to match.
+fn sanitize_table_invocation(inner: &mut Vec<Token>) -> Vec<RenamedIdent> {
+    let mut renamed = Vec::new();
+
+    let name_idx = match inner.iter().position(|t| matches!(t, Token::Ident(_))) {
+        Some(idx) => idx,
+        None => return renamed,
+    };
+
+    if let Some(brace_idx) = inner.iter().position(|t| matches!(t, Token::Brace(_))) {
+        if let Token::Brace(columns) = inner.remove(brace_idx) {
+            let (sanitized_columns, column_renames) = sanitize_column_list(columns);
+            inner.insert(brace_idx, Token::Brace(sanitized_columns));
+
+            let rename_map: std::collections::HashMap<&str, &str> = column_renames
+                .iter()
+                .map(|r| (r.original.as_str(), r.renamed.as_str()))
+                .collect();
+
+            // The primary-key tuple (if present) references column names by
+            // their original spelling; keep it in sync with the rewrite.
+            for token in &mut inner[name_idx + 1..brace_idx] {
+                if let Token::Paren(pk) = token {
+                    for pk_token in pk.iter_mut() {
+                        if let Token::Ident(pk_name) = pk_token {
+                            if let Some(new_name) = rename_map.get(pk_name.as_str()) {
+                                *pk_name = (*new_name).to_string();
+                            }
+                        }
+                    }
+                }
+            }
+
+            renamed.extend(column_renames);
+        }
+    }
+
+    if let Token::Ident(name) = inner[name_idx].clone() {
+        let (sanitized, original) = sanitize_ident(&name);
+        if let Some(original) = original {
+            inner[name_idx] = Token::Ident(sanitized.clone());
+            inner.insert(name_idx, Token::Attribute(sql_name_attribute(&original)));
+            renamed.insert(0, RenamedIdent { original, renamed: sanitized });
+        }
+    }
+
+    renamed
+}
+
+/// Splits a `{...}` column list into its comma-separated items, sanitizes
+/// every column's name as one batch (so collisions introduced by sanitizing
+/// are disambiguated across the whole table), and rebuilds the list with a
+/// `#[sql_name = "..."]` attribute inserted before each renamed column.
+fn sanitize_column_list(columns: Vec<Token>) -> (Vec<Token>, Vec<RenamedIdent>) {
+    let mut items: Vec<Vec<Token>> = vec![Vec::new()];
+    for token in columns {
+        if matches!(token, Token::Comma) {
+            items.push(Vec::new());
+        } else {
+            items.last_mut().unwrap().push(token);
+        }
+    }
+
+    // A column's name is the first `Ident` in its item; anything before it
+    // (a doc comment attached to the column) is left where it is.
+    let names: Vec<String> = items
+        .iter()
+        .filter_map(|item| {
+            item.iter().find_map(|t| match t {
+                Token::Ident(name) => Some(name.clone()),
+                _ => None,
+            })
+        })
+        .collect();
+
+    let (sanitized_names, renamed) = sanitize_idents(names.iter().map(String::as_str));
+    let mut sanitized_names = sanitized_names.into_iter();
+
+    let mut out = Vec::new();
+    for (item_idx, mut item) in items.into_iter().enumerate() {
+        if item_idx > 0 {
+            out.push(Token::Comma);
+        }
+
+        if let Some(ident_idx) = item.iter().position(|t| matches!(t, Token::Ident(_))) {
+            let original = match &item[ident_idx] {
+                Token::Ident(name) => name.clone(),
+                _ => unreachable!(),
+            };
+            let sanitized = sanitized_names.next().unwrap_or_else(|| original.clone());
+
+            if sanitized != original {
+                item[ident_idx] = Token::Ident(sanitized);
+                item.insert(ident_idx, Token::Attribute(sql_name_attribute(&original)));
+            }
+        }
+
+        out.extend(item);
+    }
+
+    (out, renamed)
+}
 
 #[cfg(test)]
 mod tests {
-    use super::format_schema;
+    use super::{
+        format_schema, format_schema_with, sanitize_ident, sanitize_idents, sql_name_attribute,
+        FormatOptions, RenamedIdent,
+    };
 
     macro_rules! test_pretty_printing {
         ($($name:ident: $input:expr => $expected:expr);*) => {
             $(
                 #[test]
                 fn $name() {
-                    let actual = format_schema($input).unwrap();
+                    let (actual, _renamed) = format_schema($input).unwrap();
                     assert_eq!($expected, actual);
                 }
             )*
@@ -178,6 +972,348 @@ r"table! {
 }";
         test_no_newline_after_comma_inside_parenthetis:
             "(a, b)" =>
-            "(a, b)"
+            "(a, b)";
+
+        test_doc_comment_passes_through:
+            "/// comment\nid -> Int4 ," =>
+            "/// comment\nid -> Int4,";
+
+        test_multiline_doc_comment_reindented:
+            "table ! { users { /// first line\n/// second line\nid -> :: diesel :: types :: Int4 , } }" =>
+r"table! {
+    users {
+        /// first line
+        /// second line
+        id -> ::diesel::types::Int4,
+    }
+}";
+
+        test_trailing_doc_comment_no_spurious_comma:
+            "table ! { users { id -> Int4 , /// trailing\n} }" =>
+r"table! {
+    users {
+        id -> Int4,
+        /// trailing
+    }
+}";
+
+        test_only_doc_comment_no_blank_line:
+            "table ! { users { /// only comment\n} }" =>
+r"table! {
+    users {
+        /// only comment
+    }
+}";
+
+        test_format_nested_generic:
+            "Array < Nullable < Integer > >" =>
+            "Array<Nullable<Integer>>";
+
+        test_format_multi_arg_generic:
+            "Map < Text , Integer >" =>
+            "Map<Text, Integer>"
+    }
+
+    #[test]
+    fn test_block_doc_comment_reindented() {
+        let (actual, _renamed) = format_schema(
+            "table ! { users { /** first line\n         second line */\nid -> Int4 , } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            r"table! {
+    users {
+        /** first line
+        second line */
+        id -> Int4,
+    }
+}",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma_forced_when_missing() {
+        let (actual, _renamed) =
+            format_schema("table ! { users { id -> Int4 , name -> Text } }").unwrap();
+
+        assert_eq!(
+            r"table! {
+    users {
+        id -> Int4,
+        name -> Text,
+    }
+}",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma_disabled() {
+        let opts = FormatOptions::new().trailing_comma(false);
+        let (actual, _renamed) =
+            format_schema_with("table ! { users { id -> Int4 , name -> Text } }", &opts).unwrap();
+
+        assert_eq!(
+            r"table! {
+    users {
+        id -> Int4,
+        name -> Text
+    }
+}",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_max_width_leaves_short_lines_alone() {
+        let opts = FormatOptions::new().max_width(80);
+        let (actual, _renamed) =
+            format_schema_with("id -> :: diesel :: types :: Int4 ,", &opts).unwrap();
+        assert_eq!("id -> ::diesel::types::Int4,", actual);
+    }
+
+    #[test]
+    fn test_max_width_wraps_long_nested_generic() {
+        let opts = FormatOptions::new().max_width(40);
+        let (actual, _renamed) = format_schema_with(
+            "created_at -> :: diesel :: types :: Nullable < :: diesel :: types :: Array \
+             < :: diesel :: types :: Integer > > ,",
+            &opts,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "created_at ->\n    ::diesel::types::Nullable<::diesel::types::Array<\n            ::diesel::types::Integer>>,",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_max_width_wraps_multi_arg_generic() {
+        let opts = FormatOptions::new().max_width(24);
+        let (actual, _renamed) =
+            format_schema_with("foo_field -> Map < Text , Integer > ,", &opts).unwrap();
+
+        assert_eq!("foo_field ->\n    Map<Text, Integer>,", actual);
+    }
+
+    #[test]
+    fn test_max_width_nested_generic_sibling_keeps_its_depth_indent() {
+        let opts = FormatOptions::new().max_width(18);
+        let (actual, _renamed) =
+            format_schema_with("field_name -> Foo < Nested < Deep > , Plain > ,", &opts).unwrap();
+
+        assert_eq!(
+            "field_name ->\n    Foo<Nested<Deep>,\n        Plain>,",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_max_width_does_not_wrap_doc_comment_containing_arrow() {
+        let opts = FormatOptions::new().max_width(20);
+        let (actual, _renamed) = format_schema_with(
+            "/// see foo -> bar for more details on this field and why it exists\n\
+             id -> Int4 ,",
+            &opts,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "/// see foo -> bar for more details on this field and why it exists\nid -> Int4,",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_sanitize_ident_leaves_valid_idents_alone() {
+        assert_eq!(("id".to_string(), None), sanitize_ident("id"));
+    }
+
+    #[test]
+    fn test_sanitize_ident_replaces_invalid_characters() {
+        assert_eq!(
+            ("user_id".to_string(), Some("user-id".to_string())),
+            sanitize_ident("user-id")
+        );
+    }
+
+    #[test]
+    fn test_sanitize_ident_escapes_leading_digit() {
+        assert_eq!(
+            ("_2fa_enabled".to_string(), Some("2fa_enabled".to_string())),
+            sanitize_ident("2fa_enabled")
+        );
+    }
+
+    #[test]
+    fn test_sanitize_ident_escapes_reserved_keyword() {
+        assert_eq!(
+            ("type_".to_string(), Some("type".to_string())),
+            sanitize_ident("type")
+        );
+    }
+
+    #[test]
+    fn test_sanitize_idents_disambiguates_collisions() {
+        let (idents, renamed) = sanitize_idents(vec!["user_id", "user-id"]);
+
+        assert_eq!(vec!["user_id".to_string(), "user_id_2".to_string()], idents);
+        assert_eq!(
+            vec![RenamedIdent {
+                original: "user-id".to_string(),
+                renamed: "user_id_2".to_string(),
+            }],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_sql_name_attribute_escapes_quotes_and_backslashes() {
+        assert_eq!(
+            r#"#[sql_name = "weird\"name\\oops"]"#,
+            sql_name_attribute(r#"weird"name\oops"#)
+        );
+    }
+
+    #[test]
+    fn test_format_schema_sanitizes_reserved_keyword_column() {
+        let (actual, renamed) =
+            format_schema("table ! { users { id -> Int4 , type -> Text } }").unwrap();
+
+        assert_eq!(
+            r#"table! {
+    users {
+        id -> Int4,
+        #[sql_name = "type"]
+        type_ -> Text,
+    }
+}"#,
+            actual
+        );
+        assert_eq!(
+            vec![RenamedIdent {
+                original: "type".to_string(),
+                renamed: "type_".to_string(),
+            }],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_format_schema_sanitizes_table_name() {
+        let (actual, renamed) = format_schema("table ! { type { id -> Int4 } }").unwrap();
+
+        assert_eq!(
+            r#"table! {
+    #[sql_name = "type"]
+    type_ {
+        id -> Int4
+    }
+}"#,
+            actual
+        );
+        assert_eq!(
+            vec![RenamedIdent {
+                original: "type".to_string(),
+                renamed: "type_".to_string(),
+            }],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_format_schema_disambiguates_and_updates_primary_key() {
+        let (actual, renamed) =
+            format_schema("table ! { users ( type ) { type -> Int4 , type_ -> Text } }").unwrap();
+
+        assert_eq!(
+            r#"table! {
+    users (type_) {
+        #[sql_name = "type"]
+        type_ -> Int4,
+        #[sql_name = "type_"]
+        type__2 -> Text,
+    }
+}"#,
+            actual
+        );
+        assert_eq!(
+            vec![
+                RenamedIdent {
+                    original: "type".to_string(),
+                    renamed: "type_".to_string(),
+                },
+                RenamedIdent {
+                    original: "type_".to_string(),
+                    renamed: "type__2".to_string(),
+                },
+            ],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_unmatched_closing_delimiter_does_not_panic() {
+        let (actual, renamed) = format_schema("abc)").unwrap();
+        assert_eq!("(abc)", actual);
+        assert!(renamed.is_empty());
+    }
+
+    #[test]
+    fn test_format_schema_sanitizes_hyphenated_column_name() {
+        let (actual, renamed) =
+            format_schema("table ! { users { user-id -> Text , } }").unwrap();
+
+        assert_eq!(
+            r#"table! {
+    users {
+        #[sql_name = "user-id"]
+        user_id -> Text,
+    }
+}"#,
+            actual
+        );
+        assert_eq!(
+            vec![RenamedIdent {
+                original: "user-id".to_string(),
+                renamed: "user_id".to_string(),
+            }],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_format_schema_sanitizes_hyphenated_table_name() {
+        let (actual, renamed) = format_schema("table ! { my-table { id -> Int4 , } }").unwrap();
+
+        assert_eq!(
+            r#"table! {
+    #[sql_name = "my-table"]
+    my_table {
+        id -> Int4,
+    }
+}"#,
+            actual
+        );
+        assert_eq!(
+            vec![RenamedIdent {
+                original: "my-table".to_string(),
+                renamed: "my_table".to_string(),
+            }],
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_format_schema_is_idempotent_with_sql_name_attribute() {
+        let (first, _renamed) =
+            format_schema("table ! { users { id -> Int4 , type -> Text } }").unwrap();
+        let (second, renamed) = format_schema(&first).unwrap();
+
+        assert_eq!(first, second);
+        assert!(renamed.is_empty());
     }
 }